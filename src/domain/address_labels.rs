@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Well-known addresses every export benefits from labelling out of the box,
+/// mirroring the kind of defaults the Solana CLI ships for common programs.
+pub fn default_labels() -> HashMap<String, String> {
+    HashMap::from([
+        ("11111111111111111111111111111111".to_string(), "System Program".to_string()),
+        ("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA".to_string(), "SPL Token Program".to_string()),
+        ("ComputeBudget111111111111111111111111111111".to_string(), "Compute Budget Program".to_string()),
+    ])
+}
+
+/// Load a pubkey -> label map from a YAML or JSON file (picked by extension),
+/// layered on top of `default_labels()` and any labels already present in the
+/// persistent config file, so user-supplied entries can override the bundled
+/// ones but never have to repeat them.
+pub fn load_labels(path: Option<&str>, config_labels: Option<&HashMap<String, String>>) -> HashMap<String, String> {
+    let mut labels = default_labels();
+
+    if let Some(config_labels) = config_labels {
+        labels.extend(config_labels.clone());
+    }
+
+    let Some(path) = path else {
+        return labels;
+    };
+
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) => {
+            log::error!("Failed to read address labels file {}: {}", path, err);
+            return labels;
+        }
+    };
+
+    let parsed: Result<HashMap<String, String>, String> = match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str(&contents).map_err(|err| err.to_string()),
+        _ => serde_yaml::from_str(&contents).map_err(|err| err.to_string()),
+    };
+
+    match parsed {
+        Ok(overrides) => labels.extend(overrides),
+        Err(err) => log::error!("Failed to parse address labels file {}: {}", path, err),
+    }
+
+    labels
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use std::fmt;
+use serde::Serialize;
+use crate::domain::TransactionRecord;
+
+/// Per-currency reconciliation row: the net change in that currency across
+/// every processed transaction.
+///
+/// This exporter never fetches the wallet's current on-chain balance, so it
+/// has no opening balance to add this to - an earlier version of this struct
+/// also carried a `closing_balance` field, but since the only balance it ever
+/// had to start from was zero, that field was always numerically identical
+/// to `net_change`. Dropped rather than faked; a real running/closing balance
+/// would need the wallet's balance as of the first processed transaction as
+/// an input.
+#[derive(Serialize)]
+pub struct BalanceSummary {
+    pub currency: String,
+    pub net_change: f64,
+}
+
+impl fmt::Display for BalanceSummary {
+    fn fmt(&self, _f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        log::info!("  {}: net change {:.9}", self.currency, self.net_change);
+        Ok(())
+    }
+}
+
+/// Walk the decoded transactions and sum, per currency, how much the wallet
+/// sent, received and paid in fees - the same reconciliation view as the
+/// "balances" report in Solana's token-distribution tooling, scoped to just
+/// the processed transactions (order doesn't matter for a sum, so unlike a
+/// true running balance this doesn't need the records in chronological order).
+pub fn compute_balances(records: &[TransactionRecord]) -> Vec<BalanceSummary> {
+    let mut balances: HashMap<String, f64> = HashMap::new();
+
+    for record in records {
+        if let (Some(amount), Some(currency)) = (record.received_amount, record.received_currency.as_ref()) {
+            *balances.entry(currency.clone()).or_insert(0.0) += amount;
+        }
+        if let (Some(amount), Some(currency)) = (record.sent_amount, record.sent_currency.as_ref()) {
+            *balances.entry(currency.clone()).or_insert(0.0) -= amount;
+        }
+        *balances.entry(record.fee_currency.clone()).or_insert(0.0) -= record.fee_amount;
+    }
+
+    let mut summaries: Vec<BalanceSummary> = balances
+        .into_iter()
+        .map(|(currency, net_change)| BalanceSummary { currency, net_change })
+        .collect();
+
+    summaries.sort_by(|a, b| a.currency.cmp(&b.currency));
+    summaries
+}
+
+pub fn log_balances_report(summaries: &[BalanceSummary]) {
+    log::info!("Balances summary:");
+    for summary in summaries {
+        log::info!("{}", summary);
+    }
+}
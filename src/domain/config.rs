@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::fs;
+use serde::Deserialize;
+use solana_sdk::commitment_config::CommitmentConfig;
+
+pub const DEFAULT_MAINNET_URL: &str = "https://api.mainnet-beta.solana.com";
+
+/// Persistent configuration loaded from a YAML file, following the model
+/// used by the Solana CLI's `config.yml`: CLI flags override these values,
+/// and these values override the built-in defaults.
+#[derive(Debug, Default, Deserialize)]
+pub struct AppConfig {
+    pub json_rpc_url: Option<String>,
+    pub address_labels: Option<HashMap<String, String>>,
+}
+
+/// Default location for the config file, mirroring `~/.config/solana/cli/config.yml`.
+pub fn default_config_path() -> String {
+    match dirs::home_dir() {
+        Some(home) => home.join(".config/solana-th-exporter/config.yaml").to_string_lossy().to_string(),
+        None => "solana-th-exporter.yaml".to_string(),
+    }
+}
+
+/// Load the config file from `path` if it's given, otherwise from the default
+/// location. Returns `AppConfig::default()` (i.e. all built-in defaults) when
+/// no file is found - the config file is optional, not required.
+pub fn load_config(path: Option<&str>) -> AppConfig {
+    let path = path.map(str::to_string).unwrap_or_else(default_config_path);
+
+    match fs::read_to_string(&path) {
+        Ok(contents) => match serde_yaml::from_str(&contents) {
+            Ok(config) => config,
+            Err(err) => {
+                log::error!("Failed to parse config file {}: {}", path, err);
+                AppConfig::default()
+            }
+        },
+        Err(_) => {
+            log::debug!("No config file found at {}, using defaults", path);
+            AppConfig::default()
+        }
+    }
+}
+
+/// Resolve a `--cluster` shortcut to its JSON RPC URL.
+pub fn cluster_url(cluster: &str) -> Option<&'static str> {
+    match cluster {
+        "mainnet-beta" => Some(DEFAULT_MAINNET_URL),
+        "devnet" => Some("https://api.devnet.solana.com"),
+        "testnet" => Some("https://api.testnet.solana.com"),
+        "localhost" => Some("http://127.0.0.1:8899"),
+        _ => None,
+    }
+}
+
+/// Resolve the effective JSON RPC URL given the CLI flags and the loaded
+/// config file, in priority order: `--url` > `--cluster` > config file >
+/// built-in mainnet-beta default.
+pub fn resolve_rpc_url(url: Option<&str>, cluster: Option<&str>, config: &AppConfig) -> String {
+    if let Some(url) = url {
+        return url.to_string();
+    }
+    if let Some(cluster) = cluster {
+        if let Some(url) = cluster_url(cluster) {
+            return url.to_string();
+        }
+        log::error!("Unknown cluster '{}', falling back to config/default", cluster);
+    }
+    config.json_rpc_url.clone().unwrap_or_else(|| DEFAULT_MAINNET_URL.to_string())
+}
+
+/// Resolve a `--commitment` flag to a `CommitmentConfig`, defaulting to
+/// `confirmed` - the same default the Solana CLI uses for most commands.
+pub fn resolve_commitment(commitment: Option<&str>) -> CommitmentConfig {
+    match commitment {
+        Some("processed") => CommitmentConfig::processed(),
+        Some("finalized") => CommitmentConfig::finalized(),
+        Some("confirmed") | None => CommitmentConfig::confirmed(),
+        Some(other) => {
+            log::error!("Unknown commitment level '{}', falling back to confirmed", other);
+            CommitmentConfig::confirmed()
+        }
+    }
+}
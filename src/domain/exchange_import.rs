@@ -0,0 +1,37 @@
+use serde::Serialize;
+use crate::domain::TransactionRecord;
+
+/// One row of the tax-tool import schema: just the columns common
+/// exchange/tax importers expect (date, type, sent/received leg, fee, tx
+/// hash), in that order - internal addresses, labels, memo, and the USD
+/// valuation stay in the full `TransactionRecord`, available via the
+/// JSON/JSONL export instead.
+#[derive(Serialize)]
+pub struct ExchangeImportRow {
+    pub date: String,
+    #[serde(rename = "type")]
+    pub transaction_type: String,
+    pub sent_amount: Option<f64>,
+    pub sent_currency: Option<String>,
+    pub received_amount: Option<f64>,
+    pub received_currency: Option<String>,
+    pub fee_amount: f64,
+    pub fee_currency: String,
+    pub tx_hash: String,
+}
+
+impl From<&TransactionRecord> for ExchangeImportRow {
+    fn from(record: &TransactionRecord) -> Self {
+        ExchangeImportRow {
+            date: record.date.clone(),
+            transaction_type: record.transaction_type.clone(),
+            sent_amount: record.sent_amount,
+            sent_currency: record.sent_currency.clone(),
+            received_amount: record.received_amount,
+            received_currency: record.received_currency.clone(),
+            fee_amount: record.fee_amount,
+            fee_currency: record.fee_currency.clone(),
+            tx_hash: record.tx_hash.clone(),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+pub mod transaction_record;
+pub mod token_registry;
+pub mod address_labels;
+pub mod config;
+pub mod balance_summary;
+pub mod exchange_import;
+
+pub use transaction_record::TransactionRecord;
+pub use exchange_import::ExchangeImportRow;
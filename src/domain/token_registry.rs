@@ -0,0 +1,28 @@
+use std::collections::HashMap;
+
+/// Bundled list of well-known SPL token mints mapped to their ticker symbol.
+/// Kept small on purpose - anything missing here just falls back to the
+/// mint address, it's only meant to cover the common case.
+pub fn bundled_registry() -> HashMap<&'static str, &'static str> {
+    HashMap::from([
+        ("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "USDC"),
+        ("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB", "USDT"),
+        ("So11111111111111111111111111111111111111112", "wSOL"),
+        ("mSoLzYCxHdYgdzU16g5QSh3i5K3z3KZK7ytfqcJm7So", "mSOL"),
+        ("7dHbWXmci3dT8UFYWYZweBLXgycu7Y3iL6trKn1Y7ARj", "stSOL"),
+        ("4k3Dyjzvzp8eMZWUXbBCjEvwSkkk59S5iCNLY3QrkX6R", "RAY"),
+    ])
+}
+
+/// Resolve a mint's ticker symbol, preferring a caller-supplied override map,
+/// then the bundled registry, and finally falling back to the mint address
+/// itself when the token is unknown.
+pub fn resolve_symbol(mint: &str, overrides: Option<&HashMap<String, String>>) -> String {
+    if let Some(symbol) = overrides.and_then(|map| map.get(mint)) {
+        return symbol.clone();
+    }
+    if let Some(symbol) = bundled_registry().get(mint) {
+        return symbol.to_string();
+    }
+    mint.to_string()
+}
@@ -7,10 +7,20 @@ pub struct TransactionRecord {
     pub tx_hash: String,
     pub tx_src: String,
     pub tx_dest: String,
+    pub tx_src_label: Option<String>,
+    pub tx_dest_label: Option<String>,
     pub sent_amount: Option<f64>,
     pub sent_currency: Option<String>,
     pub received_amount: Option<f64>,
     pub received_currency: Option<String>,
+    pub sent_value_usd: Option<f64>,
+    pub received_value_usd: Option<f64>,
+    /// TRADE/DEPOSIT/WITHDRAWAL discriminator, decided once at record
+    /// construction time from whether a sent and/or received leg is present -
+    /// see `SolanaTHService::classify_transaction`. Bridge activity is typed
+    /// "Bridge In"/"Bridge Out" instead.
+    pub transaction_type: String,
+    pub memo: Option<String>,
     pub fee_amount: f64,
     pub fee_currency: String,
 }
@@ -20,8 +30,16 @@ impl fmt::Display for TransactionRecord {
         log::info!("Transaction Record:");
         log::info!("  Date: {}", self.date);
         log::info!("  Tx Hash: {}", self.tx_hash);
-        log::info!("  Source: {}", self.tx_src);
-        log::info!("  Destination: {}", self.tx_dest);
+        log::info!(
+            "  Source: {} ({})",
+            self.tx_src,
+            self.tx_src_label.as_deref().unwrap_or("unlabeled")
+        );
+        log::info!(
+            "  Destination: {} ({})",
+            self.tx_dest,
+            self.tx_dest_label.as_deref().unwrap_or("unlabeled")
+        );
         log::info!(
             "  Sent Amount: {} {}",
             self.sent_amount.map_or("N/A".to_string(), |amt| amt.to_string()),
@@ -37,6 +55,13 @@ impl fmt::Display for TransactionRecord {
             self.fee_amount.to_string(),
             self.fee_currency
         );
+        log::info!(
+            "  Value: sent {} USD, received {} USD",
+            self.sent_value_usd.map_or("N/A".to_string(), |v| v.to_string()),
+            self.received_value_usd.map_or("N/A".to_string(), |v| v.to_string())
+        );
+        log::info!("  Type: {}", self.transaction_type);
+        log::info!("  Memo: {}", self.memo.as_deref().unwrap_or("N/A"));
         Ok(())
     }
 }
\ No newline at end of file
@@ -4,7 +4,8 @@ mod ports;
 use std::str::FromStr;
 use clap::Parser;
 use solana_sdk::pubkey::Pubkey;
-use crate::ports::{FileExporterService, SolanaTHService};
+use crate::ports::file_exporter_service::OutputFormat;
+use crate::ports::{FileExporterService, SolanaTHService, SignatureStore, RpcSettings};
 
 #[tokio::main]
 async fn main(){
@@ -26,25 +27,67 @@ async fn main(){
     match validate_address(&address) {
         Ok(valid_address) => {
             log::info!("Fetching transaction history for address: {}", valid_address);
-            let transactions = match args.operation_limit {
-                Some(limit) => {
-                    log::info!("Operation limit provided: {}", limit);
-                    SolanaTHService::fetch_transactions(valid_address, limit)
-                }
-                None => {
-                    log::info!("No operation limit provided, fetching all transactions.");
-                    SolanaTHService::fetch_transactions(valid_address,0)
-                }
+
+            let config = domain::config::load_config(args.config.as_deref());
+            let address_labels = domain::address_labels::load_labels(
+                args.address_labels.as_deref(),
+                config.address_labels.as_ref(),
+            );
+            let rpc_url = domain::config::resolve_rpc_url(
+                args.url.as_deref(),
+                args.cluster.as_deref(),
+                &config,
+            );
+            let commitment = domain::config::resolve_commitment(args.commitment.as_deref());
+            log::info!("Using RPC endpoint: {} (commitment: {:?})", rpc_url, commitment.commitment);
+
+            let rpc_settings = RpcSettings::new(rpc_url)
+                .with_commitment(commitment)
+                .with_timeout(std::time::Duration::from_secs(args.rpc_timeout_secs));
+
+            let limit = args.operation_limit.unwrap_or(0);
+            let mut signature_store = SignatureStore::load(&args.state_file);
+            let until = if args.incremental {
+                signature_store.last_signature(&address).map(str::to_string)
+            } else {
+                None
             };
 
+            let (transactions, newest_signature) = SolanaTHService::fetch_transactions_incremental(
+                valid_address,
+                limit,
+                &address_labels,
+                &rpc_settings,
+                until.as_deref(),
+            );
+
             // Proceed with fetching and exporting transactions...
             if transactions.len() > 0 {
-                let _ = FileExporterService::save_transactions_to_csv(transactions,"transactions.csv");
+                if args.balances {
+                    let summaries = domain::balance_summary::compute_balances(&transactions);
+                    domain::balance_summary::log_balances_report(&summaries);
+                    if let Err(err) = FileExporterService::save_balances_to_csv(&summaries, "balances.csv") {
+                        log::error!("Failed to write balances.csv: {}", err);
+                    }
+                }
+
+                let format = OutputFormat::resolve(args.format.as_deref(), &args.output);
+                if let Err(err) = FileExporterService::export_transactions(transactions, &args.output, format, args.incremental) {
+                    log::error!("Failed to write {}: {}", args.output, err);
+                }
             }
             else{
                 log::info!("No transactions to export");
             }
 
+            if args.incremental {
+                if let Some(newest_signature) = newest_signature {
+                    signature_store.set_last_signature(&address, newest_signature);
+                    if let Err(err) = signature_store.save() {
+                        log::error!("Failed to persist signature state to {}: {}", args.state_file, err);
+                    }
+                }
+            }
         }
         Err(err) => {
             log::error!("Error: {}", err);
@@ -71,6 +114,45 @@ struct Cli {
         value_parser = parse_positive_integer
     )]
     operation_limit: Option<usize>,
+
+    #[arg(
+        long,
+        help = "YAML/JSON file mapping pubkey -> label, layered on top of the bundled defaults"
+    )]
+    address_labels: Option<String>,
+
+    #[arg(long, help = "JSON RPC URL to query, overrides --cluster and the config file")]
+    url: Option<String>,
+
+    #[arg(
+        long,
+        help = "Cluster shortcut: mainnet-beta, devnet, testnet, or localhost"
+    )]
+    cluster: Option<String>,
+
+    #[arg(long, help = "Commitment level to query: processed, confirmed, or finalized (default: confirmed)")]
+    commitment: Option<String>,
+
+    #[arg(long, default_value_t = 30, help = "RPC request timeout in seconds")]
+    rpc_timeout_secs: u64,
+
+    #[arg(long, help = "Path to the persistent config file (default: ~/.config/solana-th-exporter/config.yaml)")]
+    config: Option<String>,
+
+    #[arg(long, help = "Only fetch transactions newer than the last recorded run, and append to the output file (CSV/JSONL; JSON output is rewritten)")]
+    incremental: bool,
+
+    #[arg(long, default_value = "signature_state.json", help = "Path to the signature state store used by --incremental")]
+    state_file: String,
+
+    #[arg(long, help = "Compute a per-currency balances/portfolio summary and write it to balances.csv")]
+    balances: bool,
+
+    #[arg(long, default_value = "transactions.csv", help = "Output file for the exported transactions")]
+    output: String,
+
+    #[arg(long, help = "Output format: csv, json, or jsonl (defaults to the --output file's extension)")]
+    format: Option<String>,
 }
 
 fn parse_positive_integer(v: &str) -> Result<usize, String> {
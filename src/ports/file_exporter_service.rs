@@ -1,41 +1,137 @@
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::path::Path;
 use std::io::Write;
 use crate::domain::TransactionRecord;
+use crate::domain::ExchangeImportRow;
+use crate::domain::balance_summary::BalanceSummary;
 
 pub struct FileExporterService;
 
-impl FileExporterService{
-
-    pub fn save_transactions_to_csv(records: Vec<TransactionRecord>, file_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let path = Path::new(file_name);
-        let mut file = File::create(&path)?;
-
-        // Zapisanie nagłówków kolumn
-        writeln!(
-            file,
-            "date,tx_hash,tx_src,tx_dest,sent_amount,sent_currency,received_amount,received_currency,fee_amount,fee_currency"
-        )?;
-
-        // Zapisanie danych
-        for record in records {
-            writeln!(
-                file,
-                "{},{},{},{},{},{},{},{},{},{}",
-                record.date,
-                record.tx_hash,
-                record.tx_src,
-                record.tx_dest,
-                record.sent_amount.map_or("N/A".to_string(), |amt| amt.to_string()),
-                record.sent_currency.as_deref().unwrap_or("N/A"),
-                record.received_amount.map_or("N/A".to_string(), |amt| amt.to_string()),
-                record.received_currency.as_deref().unwrap_or("N/A"),
-                record.fee_amount,
-                record.fee_currency,
-            )?;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Csv,
+    Json,
+    Jsonl,
+}
+
+impl OutputFormat {
+    pub fn from_flag(flag: Option<&str>) -> Option<OutputFormat> {
+        match flag {
+            Some("csv") => Some(OutputFormat::Csv),
+            Some("json") => Some(OutputFormat::Json),
+            Some("jsonl") => Some(OutputFormat::Jsonl),
+            _ => None,
+        }
+    }
+
+    /// Pick a format from the `--format` flag if given, otherwise infer it
+    /// from the output file's extension, falling back to CSV.
+    pub fn resolve(flag: Option<&str>, file_name: &str) -> OutputFormat {
+        if let Some(format) = Self::from_flag(flag) {
+            return format;
+        }
+
+        match Path::new(file_name).extension().and_then(|ext| ext.to_str()) {
+            Some("json") => OutputFormat::Json,
+            Some("jsonl") => OutputFormat::Jsonl,
+            _ => OutputFormat::Csv,
+        }
+    }
+}
+
+impl FileExporterService {
+    /// Write records to `file_name` as CSV. When `append` is true (the
+    /// `--incremental` case), this grows an existing file instead of
+    /// rewriting it from scratch, writing the header only when the file
+    /// doesn't already exist; when `append` is false, the file is truncated
+    /// first, matching a plain re-run's expectation that the output reflects
+    /// only this run's transactions, not this run's plus every prior one.
+    /// Fields are quoted/escaped per RFC 4180 via the `csv` crate, so a
+    /// free-text field such as a currency symbol can safely contain a comma
+    /// or quote.
+    ///
+    /// Rows follow `ExchangeImportRow`'s column layout rather than dumping
+    /// every `TransactionRecord` field, so the file is importable by common
+    /// tax tools as-is; the full record (internal addresses, labels, memo,
+    /// USD valuation) is still available via the JSON/JSONL export.
+    pub fn append_transactions_to_csv(records: Vec<TransactionRecord>, file_name: &str, append: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let file_existed = append && Path::new(file_name).exists();
+
+        let file = if append {
+            OpenOptions::new().create(true).append(true).open(file_name)?
+        } else {
+            OpenOptions::new().create(true).write(true).truncate(true).open(file_name)?
+        };
+
+        let mut writer = csv::WriterBuilder::new()
+            .has_headers(!file_existed)
+            .from_writer(file);
+
+        for record in &records {
+            writer.serialize(ExchangeImportRow::from(record))?;
+        }
+        writer.flush()?;
+
+        log::info!("Transactions successfully saved to {}", file_name);
+        Ok(())
+    }
+
+    /// Export transactions in the requested format. CSV and JSONL both
+    /// support appending to an existing file (the incremental-export use
+    /// case) and truncating otherwise; a JSON array is always rewritten from
+    /// scratch, since splicing a new element into an existing array isn't
+    /// something a plain append can do.
+    pub fn export_transactions(
+        records: Vec<TransactionRecord>,
+        file_name: &str,
+        format: OutputFormat,
+        append: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match format {
+            OutputFormat::Csv => Self::append_transactions_to_csv(records, file_name, append),
+            OutputFormat::Jsonl => Self::append_transactions_to_jsonl(records, file_name, append),
+            OutputFormat::Json => {
+                if append {
+                    log::warn!("JSON array output doesn't support --incremental appends, rewriting {} in full", file_name);
+                }
+                Self::save_transactions_to_json(records, file_name)
+            }
+        }
+    }
+
+    fn append_transactions_to_jsonl(records: Vec<TransactionRecord>, file_name: &str, append: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let mut file = if append {
+            OpenOptions::new().create(true).append(true).open(file_name)?
+        } else {
+            OpenOptions::new().create(true).write(true).truncate(true).open(file_name)?
+        };
+
+        for record in &records {
+            writeln!(file, "{}", serde_json::to_string(record)?)?;
         }
 
         log::info!("Transactions successfully saved to {}", file_name);
         Ok(())
     }
-}
\ No newline at end of file
+
+    fn save_transactions_to_json(records: Vec<TransactionRecord>, file_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let file = File::create(file_name)?;
+        serde_json::to_writer_pretty(file, &records)?;
+
+        log::info!("Transactions successfully saved to {}", file_name);
+        Ok(())
+    }
+
+    /// Write the balances/portfolio summary (one row per currency) to a
+    /// reconciliation CSV alongside the line-item transaction export.
+    pub fn save_balances_to_csv(summaries: &[BalanceSummary], file_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut writer = csv::Writer::from_path(file_name)?;
+        for summary in summaries {
+            writer.serialize(summary)?;
+        }
+        writer.flush()?;
+
+        log::info!("Balances successfully saved to {}", file_name);
+        Ok(())
+    }
+}
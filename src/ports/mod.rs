@@ -1,5 +1,7 @@
 pub mod solana_th_service;
 pub mod file_exporter_service;
+pub mod signature_store;
 
-pub use solana_th_service::SolanaTHService;
-pub use file_exporter_service::FileExporterService;
\ No newline at end of file
+pub use solana_th_service::{SolanaTHService, RpcSettings};
+pub use file_exporter_service::FileExporterService;
+pub use signature_store::SignatureStore;
\ No newline at end of file
@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// A tiny JSON-backed key-value store, keyed by wallet address, recording the
+/// most recent signature already exported for that wallet. This is what
+/// makes incremental re-runs cheap: the next run passes the stored signature
+/// as the `until` bound to `getSignaturesForAddress` so only new
+/// transactions are fetched.
+pub struct SignatureStore {
+    path: String,
+    entries: HashMap<String, String>,
+}
+
+impl SignatureStore {
+    pub fn load(path: &str) -> Self {
+        let entries = match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_default(),
+            Err(_) => HashMap::new(),
+        };
+
+        SignatureStore {
+            path: path.to_string(),
+            entries,
+        }
+    }
+
+    pub fn last_signature(&self, wallet: &str) -> Option<&str> {
+        self.entries.get(wallet).map(String::as_str)
+    }
+
+    pub fn set_last_signature(&mut self, wallet: &str, signature: String) {
+        self.entries.insert(wallet.to_string(), signature);
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let contents = serde_json::to_string_pretty(&self.entries)?;
+        if let Some(parent) = Path::new(&self.path).parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        fs::write(&self.path, contents)
+    }
+}